@@ -0,0 +1,8 @@
+#[cfg(feature = "pipes")]
+mod pipes;
+
+#[cfg(feature = "pipes")]
+pub use pipes::{
+    download_pipe, run_js, run_pipe, run_pipe_with_policy, update_pipe, Capabilities,
+    PermissionDenied, PermissionPolicy, PipeError,
+};
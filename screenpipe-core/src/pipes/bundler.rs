@@ -0,0 +1,516 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use sha2::{Digest, Sha256};
+use sourcemap::SourceMapBuilder;
+use swc_common::{sync::Lrc, SourceMap as SwcSourceMap};
+use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax, TsConfig};
+use swc_ecma_transforms_typescript::strip;
+use swc_ecma_visit::FoldWith;
+
+/// Where an import came from: a sibling file (resolved relative to its
+/// importer) or a remote module fetched over HTTPS, analogous to Deno's
+/// `ModuleSpecifier` resolution.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ModuleSpecifier {
+    Local(PathBuf),
+    Remote(String),
+}
+
+/// One resolved, transpiled module in the graph.
+struct Module {
+    specifier: ModuleSpecifier,
+    source: String,
+    transpiled: String,
+    /// Each import statement's raw specifier text paired with what it
+    /// resolved to, so `link` can find which bundled module a given `import`
+    /// line is asking for without re-resolving it.
+    imports: Vec<(String, ModuleSpecifier)>,
+}
+
+/// The output of bundling a pipe entrypoint: one executable JS blob plus a
+/// source map back to the original TypeScript, so `PipeError` can report
+/// author-facing line numbers.
+pub struct Bundle {
+    pub code: String,
+    pub source_map: String,
+}
+
+/// Resolves imports starting from `entrypoint`, transpiling and bundling the
+/// whole module graph into one executable unit. Remote `https://` modules are
+/// fetched once and cached under `screenpipe_dir/.cache/modules`, keyed by a
+/// hash of their URL, so repeated runs of the same pipe don't re-fetch them.
+pub async fn bundle(entrypoint: &Path, screenpipe_dir: &Path) -> Result<Bundle> {
+    let cache_dir = screenpipe_dir.join(".cache").join("modules");
+    tokio::fs::create_dir_all(&cache_dir).await.ok();
+
+    let mut graph = HashMap::new();
+    let mut order = Vec::new();
+    let root = ModuleSpecifier::Local(entrypoint.to_path_buf());
+    load_recursive(root, &cache_dir, &mut graph, &mut order).await?;
+
+    link(&graph, &order)
+}
+
+#[async_recursion::async_recursion]
+async fn load_recursive(
+    specifier: ModuleSpecifier,
+    cache_dir: &Path,
+    graph: &mut HashMap<ModuleSpecifier, Module>,
+    order: &mut Vec<ModuleSpecifier>,
+) -> Result<()> {
+    if graph.contains_key(&specifier) {
+        return Ok(());
+    }
+
+    let source = load_source(&specifier, cache_dir).await?;
+    let transpiled = transpile(&source)?;
+    let imports = parse_imports(&source);
+
+    // Reserve the slot before recursing so a cyclic import graph can't loop
+    // forever re-visiting the same specifier.
+    graph.insert(
+        specifier.clone(),
+        Module {
+            specifier: specifier.clone(),
+            source,
+            transpiled,
+            imports: Vec::new(),
+        },
+    );
+
+    let mut resolved_imports = Vec::with_capacity(imports.len());
+    for import in imports {
+        let resolved = resolve(&specifier, &import)?;
+        resolved_imports.push((import, resolved.clone()));
+        load_recursive(resolved, cache_dir, graph, order).await?;
+    }
+
+    graph
+        .get_mut(&specifier)
+        .expect("just inserted above")
+        .imports = resolved_imports;
+
+    order.push(specifier);
+    Ok(())
+}
+
+async fn load_source(specifier: &ModuleSpecifier, cache_dir: &Path) -> Result<String> {
+    match specifier {
+        ModuleSpecifier::Local(path) => tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("reading module {}", path.display())),
+        ModuleSpecifier::Remote(url) => {
+            let cache_path = cache_dir.join(cache_key(url));
+            if let Ok(cached) = tokio::fs::read_to_string(&cache_path).await {
+                return Ok(cached);
+            }
+            let body = reqwest::get(url)
+                .await
+                .with_context(|| format!("fetching module {url}"))?
+                .error_for_status()?
+                .text()
+                .await?;
+            tokio::fs::write(&cache_path, &body).await.ok();
+            Ok(body)
+        }
+    }
+}
+
+fn cache_key(url: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(url.as_bytes());
+    format!("{:x}.ts", hasher.finalize())
+}
+
+/// Resolves an import string relative to `importer`: a bare `https://` URL
+/// stays remote, a relative path resolves against the importer's directory
+/// (staying remote if the importer itself was remote), matching how Deno
+/// resolves specifiers.
+fn resolve(importer: &ModuleSpecifier, import: &str) -> Result<ModuleSpecifier> {
+    if import.starts_with("https://") || import.starts_with("http://") {
+        return Ok(ModuleSpecifier::Remote(import.to_string()));
+    }
+
+    match importer {
+        ModuleSpecifier::Local(path) => {
+            let dir = path.parent().ok_or_else(|| anyhow!("module has no parent dir"))?;
+            Ok(ModuleSpecifier::Local(normalize_ts_path(&dir.join(import))))
+        }
+        ModuleSpecifier::Remote(base) => {
+            let joined = url::Url::parse(base)?
+                .join(import)
+                .with_context(|| format!("resolving `{import}` against `{base}`"))?;
+            Ok(ModuleSpecifier::Remote(joined.to_string()))
+        }
+    }
+}
+
+fn normalize_ts_path(path: &Path) -> PathBuf {
+    if path.extension().is_none() {
+        path.with_extension("ts")
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Scans for top-level `import ... from "specifier"` / `export ... from "specifier"`
+/// statements. Pipes use a small, static subset of ES module syntax, so a
+/// line-oriented scan is enough -- a full resolver would walk the parsed AST
+/// instead of the raw source.
+fn parse_imports(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if !(line.starts_with("import ") || line.starts_with("export ")) {
+                return None;
+            }
+            if line.starts_with("export ") && !line.contains(" from ") {
+                return None;
+            }
+            let quote_start = line.find(['"', '\''])?;
+            let quote_char = line.as_bytes()[quote_start] as char;
+            let rest = &line[quote_start + 1..];
+            let quote_end = rest.find(quote_char)?;
+            Some(rest[..quote_end].to_string())
+        })
+        .collect()
+}
+
+/// Strips TypeScript types via swc, producing plain executable JS.
+fn transpile(source: &str) -> Result<String> {
+    let cm: Lrc<SwcSourceMap> = Default::default();
+    let fm = cm.new_source_file(swc_common::FileName::Anon, source.to_string());
+
+    let lexer = Lexer::new(
+        Syntax::Typescript(TsConfig::default()),
+        Default::default(),
+        StringInput::from(&*fm),
+        None,
+    );
+    let mut parser = Parser::new_from(lexer);
+    let module = parser
+        .parse_module()
+        .map_err(|e| anyhow!("parsing TypeScript module: {e:?}"))?;
+
+    let stripped = module.fold_with(&mut strip());
+
+    let mut buf = vec![];
+    {
+        let mut emitter = swc_ecma_codegen::Emitter {
+            cfg: Default::default(),
+            cm: cm.clone(),
+            comments: None,
+            wr: Box::new(swc_ecma_codegen::text_writer::JsWriter::new(cm.clone(), "\n", &mut buf, None)),
+        };
+        emitter.emit_module(&stripped)?;
+    }
+
+    Ok(String::from_utf8(buf)?)
+}
+
+/// Lines prepended before a module's own body in the bundle: the IIFE opener
+/// and the `__exports` initializer. Every mapped source-map line has to be
+/// shifted by this much, or every reported frame lands this many lines below
+/// the statement that actually threw.
+const PRELUDE_LINES: u32 = 2;
+
+/// An `export`ed binding: the name it's exposed under on the module's
+/// `__exports` object, and the name of the local variable/function/class that
+/// holds the value.
+struct ExportBinding {
+    exported_name: String,
+    local_name: String,
+}
+
+/// A module's transpiled body with `import`/`export` statements rewritten
+/// into plain statements that read/write the bundle's `__mod_N.__exports`
+/// objects, plus the bindings still left to attach to this module's own
+/// `__exports` once the body has run.
+struct RewrittenModule {
+    body: String,
+    exports: Vec<ExportBinding>,
+}
+
+/// Concatenates every transpiled module in dependency order into one
+/// executable script. Each module is wrapped in its own `async function`
+/// IIFE so its top-level scope doesn't leak into the others and so it can
+/// `await` before producing its exports; `import`/`export` declarations
+/// (illegal outside real ES module syntax) are rewritten into references to
+/// the corresponding sibling module's `__exports` object. Builds a combined
+/// source map offsetting each module's contribution by where it landed in
+/// the bundle.
+fn link(graph: &HashMap<ModuleSpecifier, Module>, order: &[ModuleSpecifier]) -> Result<Bundle> {
+    let index_of: HashMap<ModuleSpecifier, usize> =
+        order.iter().enumerate().map(|(i, s)| (s.clone(), i)).collect();
+
+    let mut code = String::new();
+    let mut builder = SourceMapBuilder::new(None);
+    // The runtime executes the bundle as a classic script, where `await` is
+    // only an operator inside an async function, so the whole bundle runs
+    // inside one outer `async` arrow. Its opening line precedes every module.
+    code.push_str("(async () => {\n");
+    let mut line_offset = 1u32;
+
+    for (idx, specifier) in order.iter().enumerate() {
+        let module = graph
+            .get(specifier)
+            .ok_or_else(|| anyhow!("missing module in graph"))?;
+
+        let source_id = builder.add_source(&module_name(&module.specifier));
+        builder.set_source_contents(source_id, Some(&module.source));
+
+        let rewritten = rewrite_module(&module.transpiled, &module.imports, &index_of);
+
+        for (line_no, _) in rewritten.body.lines().enumerate() {
+            builder.add_raw(
+                line_offset + PRELUDE_LINES + line_no as u32,
+                0,
+                line_no as u32,
+                0,
+                Some(source_id),
+                None,
+            );
+        }
+
+        // `async` so pipes using top-level `await` (the overwhelming common
+        // case -- almost every real pipe awaits `pipe.get`/`pipe.fs.*`
+        // immediately) don't hit a `SyntaxError` from a plain function
+        // wrapper. `__exports` is what this module's `import`ers read from.
+        code.push_str(&format!("const __mod_{idx} = await (async function () {{\n"));
+        code.push_str("  const __exports = {};\n");
+        code.push_str(&rewritten.body);
+        code.push('\n');
+        for export in &rewritten.exports {
+            code.push_str(&format!("  __exports.{} = {};\n", export.exported_name, export.local_name));
+        }
+        code.push_str("  return __exports;\n");
+        code.push_str("})();\n");
+
+        line_offset +=
+            PRELUDE_LINES + rewritten.body.lines().count() as u32 + rewritten.exports.len() as u32 + 2;
+    }
+    code.push_str("})();\n");
+
+    let mut map_bytes = vec![];
+    builder.into_sourcemap().to_writer(&mut map_bytes)?;
+
+    Ok(Bundle {
+        code,
+        source_map: String::from_utf8(map_bytes)?,
+    })
+}
+
+/// Rewrites a module's `import`/`export` statements into plain statements
+/// referencing the bundle's `__mod_N` objects, line by line -- matching
+/// `parse_imports`, pipes use a small, static subset of ES module syntax, so
+/// a full AST rewrite isn't needed. `export ... from "specifier"` re-exports
+/// aren't handled (not used by any pipe this bundler has seen); that line is
+/// passed through unchanged.
+fn rewrite_module(
+    transpiled: &str,
+    imports: &[(String, ModuleSpecifier)],
+    index_of: &HashMap<ModuleSpecifier, usize>,
+) -> RewrittenModule {
+    let mut body_lines = Vec::new();
+    let mut exports = Vec::new();
+
+    for line in transpiled.lines() {
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with("import ") || trimmed.starts_with("import\"") || trimmed.starts_with("import'") {
+            if let Some(rewritten) = rewrite_import(trimmed, imports, index_of) {
+                body_lines.push(rewritten);
+            }
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("export default ") {
+            let rest = rest.trim_end_matches(';').trim();
+            match default_decl_name(rest) {
+                Some(name) => {
+                    body_lines.push(rest.to_string());
+                    exports.push(ExportBinding {
+                        exported_name: "default".to_string(),
+                        local_name: name,
+                    });
+                }
+                None => {
+                    body_lines.push(format!("const __default = {rest};"));
+                    exports.push(ExportBinding {
+                        exported_name: "default".to_string(),
+                        local_name: "__default".to_string(),
+                    });
+                }
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("export {") && !trimmed.contains(" from ") {
+            if let Some(named) = trimmed.strip_prefix("export {").and_then(|s| s.split('}').next()) {
+                for part in named.split(',') {
+                    let part = part.trim();
+                    if part.is_empty() {
+                        continue;
+                    }
+                    let (local, exported) = match part.split_once(" as ") {
+                        Some((local, exported)) => (local.trim().to_string(), exported.trim().to_string()),
+                        None => (part.to_string(), part.to_string()),
+                    };
+                    exports.push(ExportBinding {
+                        exported_name: exported,
+                        local_name: local,
+                    });
+                }
+            }
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("export ") {
+            if (rest.starts_with('{') || rest.starts_with('*')) && rest.contains(" from ") {
+                body_lines.push(line.to_string());
+                continue;
+            }
+            if let Some(name) = decl_name(rest) {
+                exports.push(ExportBinding {
+                    exported_name: name.clone(),
+                    local_name: name,
+                });
+            }
+            body_lines.push(rest.to_string());
+            continue;
+        }
+
+        body_lines.push(line.to_string());
+    }
+
+    RewrittenModule {
+        body: body_lines.join("\n"),
+        exports,
+    }
+}
+
+/// Rewrites one `import` line into plain `const` bindings against the
+/// resolved module's `__mod_N` export object. Returns `None` for a
+/// side-effect-only import (`import "./setup.ts"`) -- dependency order
+/// already guarantees that module ran before this one, so there's nothing
+/// left to bind.
+fn rewrite_import(
+    line: &str,
+    imports: &[(String, ModuleSpecifier)],
+    index_of: &HashMap<ModuleSpecifier, usize>,
+) -> Option<String> {
+    let line = line.trim_end_matches(';').trim();
+    let rest = line.strip_prefix("import")?.trim();
+
+    let (clause, source_literal) = if rest.starts_with('"') || rest.starts_with('\'') {
+        (None, strip_quotes(rest)?)
+    } else {
+        let (clause, tail) = rest.split_once(" from ")?;
+        (Some(clause.trim()), strip_quotes(tail.trim())?)
+    };
+    let clause = clause?;
+
+    let resolved = &imports.iter().find(|(text, _)| text == &source_literal)?.1;
+    let idx = *index_of.get(resolved)?;
+    let mod_var = format!("__mod_{idx}");
+
+    if let Some(namespace) = clause.strip_prefix("* as ") {
+        // The namespace object *is* the module's exports object.
+        return Some(format!("const {} = {mod_var};", namespace.trim()));
+    }
+
+    let mut bindings = Vec::new();
+    if let Some(brace_start) = clause.find('{') {
+        let default_part = clause[..brace_start].trim().trim_end_matches(',').trim();
+        if !default_part.is_empty() {
+            bindings.push(format!("const {default_part} = {mod_var}.default;"));
+        }
+
+        let brace_end = clause.rfind('}')?;
+        let destructured: Vec<String> = clause[brace_start + 1..brace_end]
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|part| match part.split_once(" as ") {
+                Some((orig, alias)) => format!("{}: {}", orig.trim(), alias.trim()),
+                None => part.to_string(),
+            })
+            .collect();
+        if !destructured.is_empty() {
+            bindings.push(format!("const {{ {} }} = {mod_var};", destructured.join(", ")));
+        }
+    } else if !clause.is_empty() {
+        bindings.push(format!("const {} = {mod_var}.default;", clause.trim()));
+    }
+
+    Some(bindings.join("\n"))
+}
+
+fn strip_quotes(s: &str) -> Option<String> {
+    let s = s.trim();
+    match s.chars().next() {
+        Some('"') | Some('\'') => Some(s.trim_matches(|c| c == '"' || c == '\'').to_string()),
+        _ => None,
+    }
+}
+
+/// Extracts the bound name from a declaration statement, e.g. `const x = 1`
+/// -> `x`, `function foo() {` -> `foo`, `class Foo {` -> `Foo`.
+fn decl_name(rest: &str) -> Option<String> {
+    let rest = rest.trim();
+    let rest = rest.strip_prefix("async ").unwrap_or(rest);
+
+    for kw in ["const ", "let ", "var "] {
+        if let Some(after) = rest.strip_prefix(kw) {
+            return first_token(after, &['=', ':']);
+        }
+    }
+    for kw in ["function* ", "function "] {
+        if let Some(after) = rest.strip_prefix(kw) {
+            return first_token(after, &['(']);
+        }
+    }
+    if let Some(after) = rest.strip_prefix("class ") {
+        return first_token(after, &['{']);
+    }
+
+    None
+}
+
+/// Like [`decl_name`] but for `export default`, which only binds a name when
+/// the default export is itself a named function/class declaration rather
+/// than an arbitrary expression.
+fn default_decl_name(rest: &str) -> Option<String> {
+    let rest = rest.trim();
+    let rest = rest.strip_prefix("async ").unwrap_or(rest);
+
+    for kw in ["function* ", "function "] {
+        if let Some(after) = rest.strip_prefix(kw) {
+            if let Some(name) = first_token(after, &['(']) {
+                return Some(name);
+            }
+        }
+    }
+    if let Some(after) = rest.strip_prefix("class ") {
+        return first_token(after, &['{']);
+    }
+
+    None
+}
+
+fn first_token(s: &str, stop_chars: &[char]) -> Option<String> {
+    let token = s
+        .split(|c: char| c.is_whitespace() || stop_chars.contains(&c))
+        .next()?;
+    (!token.is_empty()).then(|| token.to_string())
+}
+
+fn module_name(specifier: &ModuleSpecifier) -> String {
+    match specifier {
+        ModuleSpecifier::Local(path) => path.display().to_string(),
+        ModuleSpecifier::Remote(url) => url.clone(),
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+
+use sourcemap::SourceMap;
+
+/// A single frame of a JS stack trace, already remapped through a source map
+/// when one was available so it points at the author's original TypeScript
+/// rather than the transpiled JS screenpipe actually ran.
+#[derive(Debug, Clone)]
+pub struct JsStackFrame {
+    pub function_name: Option<String>,
+    pub file_name: Option<String>,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for JsStackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let func = self.function_name.as_deref().unwrap_or("<anonymous>");
+        let file = self.file_name.as_deref().unwrap_or("<pipe>");
+        write!(f, "    at {func} ({file}:{}:{})", self.line, self.column)
+    }
+}
+
+/// An uncaught exception or rejected top-level promise from a pipe's JS
+/// runtime, carrying the original message plus a (possibly source-mapped)
+/// stack trace. Returned from `run_js`/`run_pipe` as `Err(PipeError)` instead
+/// of being swallowed into a generic error.
+#[derive(Debug, Clone)]
+pub struct PipeError {
+    pub message: String,
+    pub frames: Vec<JsStackFrame>,
+}
+
+impl PipeError {
+    /// Parses a V8 `error.stack` string (`"Error: message\n    at foo (file:1:2)"`)
+    /// into a structured [`PipeError`], remapping each frame's line/column
+    /// through `source_map` when one is given.
+    pub fn from_v8_stack(message: impl Into<String>, stack: &str, source_map: Option<&SourceMap>) -> Self {
+        let frames = stack
+            .lines()
+            .skip(1) // first line is "Error: message", already captured separately
+            .filter_map(|line| parse_stack_line(line.trim()))
+            .map(|mut frame| {
+                if let Some(sm) = source_map {
+                    if let Some(token) = sm.lookup_token(frame.line.saturating_sub(1), frame.column) {
+                        frame.file_name = token.get_source().map(str::to_string);
+                        frame.line = token.get_src_line() + 1;
+                        frame.column = token.get_src_col();
+                    }
+                }
+                frame
+            })
+            .collect();
+
+        Self {
+            message: message.into(),
+            frames,
+        }
+    }
+
+    /// Renders the error the way `run_pipe` logs it: a colorized
+    /// message line followed by one dimmed line per frame.
+    pub fn colorized_dump(&self) -> String {
+        use std::fmt::Write;
+        let mut out = String::new();
+        let _ = writeln!(out, "\x1b[31merror:\x1b[0m {}", self.message);
+        for frame in &self.frames {
+            let _ = writeln!(out, "\x1b[2m{frame}\x1b[0m");
+        }
+        out
+    }
+}
+
+impl fmt::Display for PipeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.message)?;
+        for frame in &self.frames {
+            writeln!(f, "{frame}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for PipeError {}
+
+impl From<anyhow::Error> for PipeError {
+    /// Wraps a plain plumbing error (I/O, JSON parsing, etc.) that never
+    /// reached the JS isolate, so it carries no stack frames.
+    fn from(err: anyhow::Error) -> Self {
+        Self {
+            message: err.to_string(),
+            frames: vec![],
+        }
+    }
+}
+
+/// Parses one `    at name (file:line:col)` or `    at file:line:col` frame.
+fn parse_stack_line(line: &str) -> Option<JsStackFrame> {
+    let line = line.strip_prefix("at ")?;
+    let (function_name, location) = match line.rsplit_once(" (") {
+        Some((name, loc)) => (Some(name.to_string()), loc.trim_end_matches(')')),
+        None => (None, line),
+    };
+
+    let mut parts = location.rsplitn(3, ':');
+    let column: u32 = parts.next()?.parse().ok()?;
+    let line_no: u32 = parts.next()?.parse().ok()?;
+    let file_name = parts.next().map(str::to_string);
+
+    Some(JsStackFrame {
+        function_name,
+        file_name,
+        line: line_no,
+        column,
+    })
+}
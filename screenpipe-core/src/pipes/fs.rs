@@ -0,0 +1,245 @@
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::UNIX_EPOCH;
+
+use anyhow::{anyhow, Result};
+use deno_core::{op2, OpState};
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use super::runtime::PipeRuntimeState;
+
+/// The kind of filesystem entry a [`DirEntry`] or [`Metadata`] describes.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileType {
+    File,
+    Dir,
+    Symlink,
+}
+
+impl From<std::fs::FileType> for FileType {
+    fn from(ft: std::fs::FileType) -> Self {
+        if ft.is_dir() {
+            FileType::Dir
+        } else if ft.is_symlink() {
+            FileType::Symlink
+        } else {
+            FileType::File
+        }
+    }
+}
+
+/// One entry yielded by `pipe.fs.readDir`, mirroring distant-core's walk output.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirEntry {
+    pub path: String,
+    pub file_type: FileType,
+    pub depth: usize,
+}
+
+/// File metadata returned by `pipe.fs.metadata`. Timestamps are milliseconds
+/// since the Unix epoch, `None` when the platform doesn't track that field.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metadata {
+    pub len: u64,
+    pub accessed: Option<u128>,
+    pub modified: Option<u128>,
+    pub created: Option<u128>,
+    pub file_type: FileType,
+    pub readonly: bool,
+}
+
+fn to_millis(time: std::io::Result<std::time::SystemTime>) -> Option<u128> {
+    time.ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis())
+}
+
+fn resolved_write(state: &PipeRuntimeState, path: &str) -> Result<std::path::PathBuf> {
+    state
+        .capabilities
+        .check_write(&state.screenpipe_dir, path)
+        .map_err(|e| anyhow!("{e}"))
+}
+
+fn resolved_read(state: &PipeRuntimeState, path: &str) -> Result<std::path::PathBuf> {
+    state
+        .capabilities
+        .check_read(&state.screenpipe_dir, path)
+        .map_err(|e| anyhow!("{e}"))
+}
+
+#[op2(async)]
+#[buffer]
+async fn op_pipe_fs_read_file(
+    state: Rc<RefCell<OpState>>,
+    #[string] path: String,
+) -> Result<Vec<u8>> {
+    let resolved = resolved_read(state.borrow().borrow::<PipeRuntimeState>(), &path)?;
+    Ok(tokio::fs::read(resolved).await?)
+}
+
+#[op2(async)]
+#[string]
+async fn op_pipe_fs_read_file_text(
+    state: Rc<RefCell<OpState>>,
+    #[string] path: String,
+) -> Result<String> {
+    let resolved = resolved_read(state.borrow().borrow::<PipeRuntimeState>(), &path)?;
+    Ok(tokio::fs::read_to_string(resolved).await?)
+}
+
+#[op2(async)]
+async fn op_pipe_fs_write_file(
+    state: Rc<RefCell<OpState>>,
+    #[string] path: String,
+    #[buffer] contents: Vec<u8>,
+) -> Result<()> {
+    let resolved = resolved_write(state.borrow().borrow::<PipeRuntimeState>(), &path)?;
+    tokio::fs::write(resolved, contents).await?;
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_pipe_fs_append_file(
+    state: Rc<RefCell<OpState>>,
+    #[string] path: String,
+    #[buffer] contents: Vec<u8>,
+) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+    let resolved = resolved_write(state.borrow().borrow::<PipeRuntimeState>(), &path)?;
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(resolved)
+        .await?;
+    file.write_all(&contents).await?;
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_pipe_fs_create_dir(
+    state: Rc<RefCell<OpState>>,
+    #[string] path: String,
+) -> Result<()> {
+    let resolved = resolved_write(state.borrow().borrow::<PipeRuntimeState>(), &path)?;
+    tokio::fs::create_dir_all(resolved).await?;
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_pipe_fs_remove(state: Rc<RefCell<OpState>>, #[string] path: String) -> Result<()> {
+    let resolved = resolved_write(state.borrow().borrow::<PipeRuntimeState>(), &path)?;
+    let meta = tokio::fs::symlink_metadata(&resolved).await?;
+    if meta.is_dir() {
+        tokio::fs::remove_dir_all(resolved).await?;
+    } else {
+        tokio::fs::remove_file(resolved).await?;
+    }
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_pipe_fs_rename(
+    state: Rc<RefCell<OpState>>,
+    #[string] from: String,
+    #[string] to: String,
+) -> Result<()> {
+    let (from_resolved, to_resolved) = {
+        let state = state.borrow();
+        let state = state.borrow::<PipeRuntimeState>();
+        (resolved_write(state, &from)?, resolved_write(state, &to)?)
+    };
+    tokio::fs::rename(from_resolved, to_resolved).await?;
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_pipe_fs_copy(
+    state: Rc<RefCell<OpState>>,
+    #[string] from: String,
+    #[string] to: String,
+) -> Result<u64> {
+    let (from_resolved, to_resolved) = {
+        let state = state.borrow();
+        let state = state.borrow::<PipeRuntimeState>();
+        (resolved_read(state, &from)?, resolved_write(state, &to)?)
+    };
+    Ok(tokio::fs::copy(from_resolved, to_resolved).await?)
+}
+
+#[op2(async)]
+#[serde]
+async fn op_pipe_fs_metadata(
+    state: Rc<RefCell<OpState>>,
+    #[string] path: String,
+) -> Result<Metadata> {
+    let resolved = resolved_read(state.borrow().borrow::<PipeRuntimeState>(), &path)?;
+    let meta = tokio::fs::metadata(resolved).await?;
+    Ok(Metadata {
+        len: meta.len(),
+        accessed: to_millis(meta.accessed()),
+        modified: to_millis(meta.modified()),
+        created: to_millis(meta.created()),
+        file_type: meta.file_type().into(),
+        readonly: meta.permissions().readonly(),
+    })
+}
+
+#[op2(async)]
+#[serde]
+async fn op_pipe_fs_read_dir(
+    state: Rc<RefCell<OpState>>,
+    #[string] path: String,
+    recursive: bool,
+) -> Result<Vec<DirEntry>> {
+    let (resolved, root) = {
+        let state = state.borrow();
+        let state = state.borrow::<PipeRuntimeState>();
+        (resolved_read(state, &path)?, state.screenpipe_dir.clone())
+    };
+
+    let max_depth = if recursive { usize::MAX } else { 1 };
+    let entries = WalkDir::new(&resolved)
+        .min_depth(1)
+        .max_depth(max_depth)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .map(|e| DirEntry {
+            path: relative_display(&root, e.path()),
+            file_type: e.file_type().into(),
+            depth: e.depth(),
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+fn relative_display(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+deno_core::extension!(
+    pipe_fs,
+    ops = [
+        op_pipe_fs_read_file,
+        op_pipe_fs_read_file_text,
+        op_pipe_fs_write_file,
+        op_pipe_fs_append_file,
+        op_pipe_fs_create_dir,
+        op_pipe_fs_remove,
+        op_pipe_fs_rename,
+        op_pipe_fs_copy,
+        op_pipe_fs_metadata,
+        op_pipe_fs_read_dir,
+    ],
+    esm_entry_point = "ext:pipe_fs/fs.js",
+    esm = [dir "src/pipes/js", "fs.js"],
+);
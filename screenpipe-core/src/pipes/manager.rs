@@ -0,0 +1,334 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+/// What a pipe was installed from, recorded in its `pipe.lock` so
+/// `update_pipe` knows how to re-resolve it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum Source {
+    GithubFolder { subpath: String },
+    RawFile {
+        #[serde(rename = "fileName")]
+        file_name: String,
+    },
+}
+
+/// Written alongside `pipe.json` after a successful install, pinning exactly
+/// which commit the pipe's files came from so re-installing the same ref is
+/// reproducible. A re-install still resolves the ref through the GitHub API
+/// first, so it needs network access even when the files are cached.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PipeLock {
+    url: String,
+    owner: String,
+    repo: String,
+    /// The tag/branch/SHA the user asked for, e.g. `"main"` or `"v1.2.0"`.
+    git_ref: String,
+    /// What `git_ref` resolved to at install time.
+    resolved_sha: String,
+    source: Source,
+}
+
+/// Downloads a pipe from a GitHub tree URL (a pipe folder: only the files
+/// directly inside it are fetched, its subdirectories are skipped) or a raw
+/// file URL (a single pipe script), pinning it to the commit its ref
+/// resolved to. Repeated installs of the same ref are no-ops: the pipe is
+/// cached under a content/ref-addressed directory keyed by the resolved
+/// commit SHA, so a second call never re-downloads the pipe's files --
+/// resolving the ref still needs one GitHub API call, since that's the only
+/// way to know whether a branch has moved.
+pub async fn download_pipe(url: &str, screenpipe_dir: PathBuf) -> Result<PathBuf> {
+    if let Some((owner, repo, git_ref, subpath)) = parse_github_tree_url(url) {
+        let sha = resolve_ref(&owner, &repo, &git_ref).await?;
+        install_github_folder(url, &owner, &repo, &git_ref, &sha, &subpath, screenpipe_dir).await
+    } else if let Some((owner, repo, git_ref, file_path)) = parse_raw_url(url) {
+        let sha = resolve_ref(&owner, &repo, &git_ref).await?;
+        install_raw_file(url, &owner, &repo, &git_ref, &sha, &file_path, screenpipe_dir).await
+    } else if url.starts_with("http://") || url.starts_with("https://") {
+        // A raw file host other than raw.githubusercontent.com: still
+        // downloadable, just not pin-able to a commit.
+        download_raw_file_unpinned(url, screenpipe_dir).await
+    } else {
+        Err(anyhow!("unsupported pipe url: {url}"))
+    }
+}
+
+/// Re-resolves an installed pipe's `git_ref` (typically a branch) to its
+/// latest commit SHA and, if it moved, re-downloads into the new
+/// ref-addressed cache directory. Returns the directory the pipe now lives
+/// in -- the same one as before if nothing changed.
+pub async fn update_pipe(pipe_dir: PathBuf, screenpipe_dir: PathBuf) -> Result<PathBuf> {
+    let lock: PipeLock = serde_json::from_str(
+        &tokio::fs::read_to_string(pipe_dir.join("pipe.lock"))
+            .await
+            .with_context(|| format!("reading pipe.lock in {}", pipe_dir.display()))?,
+    )?;
+
+    let latest_sha = resolve_ref(&lock.owner, &lock.repo, &lock.git_ref).await?;
+    if latest_sha == lock.resolved_sha {
+        return Ok(pipe_dir);
+    }
+
+    match &lock.source {
+        Source::GithubFolder { subpath } => {
+            install_github_folder(
+                &lock.url,
+                &lock.owner,
+                &lock.repo,
+                &lock.git_ref,
+                &latest_sha,
+                subpath,
+                screenpipe_dir,
+            )
+            .await
+        }
+        Source::RawFile { .. } => {
+            install_raw_file(
+                &lock.url,
+                &lock.owner,
+                &lock.repo,
+                &lock.git_ref,
+                &latest_sha,
+                &raw_file_path_from_url(&lock.url)?,
+                screenpipe_dir,
+            )
+            .await
+        }
+    }
+}
+
+/// Keys the cache by everything that determines a pipe's contents: the
+/// commit and the path within the repo. Two pipes living in the same repo at
+/// the same ref (e.g. two example pipes in a monorepo) must not collide.
+fn cache_dir(screenpipe_dir: &std::path::Path, owner: &str, repo: &str, sha: &str, sub: &str) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(sub.as_bytes());
+    let sub_hash = format!("{:x}", hasher.finalize());
+
+    screenpipe_dir
+        .join("pipes")
+        .join(".cache")
+        .join(format!("{owner}-{repo}-{sha}-{}", &sub_hash[..12]))
+}
+
+async fn install_github_folder(
+    url: &str,
+    owner: &str,
+    repo: &str,
+    git_ref: &str,
+    sha: &str,
+    subpath: &str,
+    screenpipe_dir: PathBuf,
+) -> Result<PathBuf> {
+    let pipe_dir = cache_dir(&screenpipe_dir, owner, repo, sha, subpath);
+
+    if pipe_dir.join("pipe.lock").exists() {
+        info!("pipe {owner}/{repo}@{sha} already cached, skipping download");
+        return Ok(pipe_dir);
+    }
+    tokio::fs::create_dir_all(&pipe_dir).await?;
+
+    let api_url =
+        format!("https://api.github.com/repos/{owner}/{repo}/contents/{subpath}?ref={sha}");
+    info!("listing github folder: {api_url}");
+
+    let entries: Vec<GithubContentEntry> = github_client()
+        .get(&api_url)
+        .send()
+        .await?
+        .json()
+        .await
+        .with_context(|| format!("listing {api_url}"))?;
+
+    for entry in entries {
+        if entry.r#type != "file" {
+            continue;
+        }
+        let Some(download_url) = entry.download_url else {
+            continue;
+        };
+        let contents = reqwest::get(&download_url).await?.bytes().await?;
+        tokio::fs::write(pipe_dir.join(&entry.name), contents).await?;
+    }
+
+    write_lock(
+        &pipe_dir,
+        PipeLock {
+            url: url.to_string(),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            git_ref: git_ref.to_string(),
+            resolved_sha: sha.to_string(),
+            source: Source::GithubFolder {
+                subpath: subpath.to_string(),
+            },
+        },
+    )
+    .await?;
+
+    Ok(pipe_dir)
+}
+
+async fn install_raw_file(
+    url: &str,
+    owner: &str,
+    repo: &str,
+    git_ref: &str,
+    sha: &str,
+    file_path: &str,
+    screenpipe_dir: PathBuf,
+) -> Result<PathBuf> {
+    let pipe_dir = cache_dir(&screenpipe_dir, owner, repo, sha, file_path);
+
+    let file_name = file_path
+        .rsplit('/')
+        .next()
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| anyhow!("could not determine file name from {url}"))?;
+
+    if pipe_dir.join("pipe.lock").exists() {
+        info!("pipe {owner}/{repo}@{sha} already cached, skipping download");
+        return Ok(pipe_dir);
+    }
+    tokio::fs::create_dir_all(&pipe_dir).await?;
+
+    let pinned_url =
+        format!("https://raw.githubusercontent.com/{owner}/{repo}/{sha}/{file_path}");
+    let contents = reqwest::get(&pinned_url)
+        .await
+        .with_context(|| format!("downloading {pinned_url}"))?
+        .error_for_status()
+        .with_context(|| format!("downloading {pinned_url}"))?
+        .bytes()
+        .await?;
+    tokio::fs::write(pipe_dir.join(file_name), contents).await?;
+
+    write_lock(
+        &pipe_dir,
+        PipeLock {
+            url: url.to_string(),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            git_ref: git_ref.to_string(),
+            resolved_sha: sha.to_string(),
+            source: Source::RawFile {
+                file_name: file_name.to_string(),
+            },
+        },
+    )
+    .await?;
+
+    Ok(pipe_dir)
+}
+
+/// Fallback for raw file hosts we can't pin to a commit (not GitHub). No
+/// `pipe.lock` is written since there's no ref to re-resolve.
+async fn download_raw_file_unpinned(url: &str, screenpipe_dir: PathBuf) -> Result<PathBuf> {
+    let file_name = url
+        .rsplit('/')
+        .next()
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| anyhow!("could not determine file name from {url}"))?;
+    let pipe_name = file_name.split('.').next().unwrap_or(file_name);
+    let pipe_dir = screenpipe_dir.join("pipes").join(pipe_name);
+    tokio::fs::create_dir_all(&pipe_dir).await?;
+
+    let contents = reqwest::get(url)
+        .await
+        .with_context(|| format!("downloading {url}"))?
+        .error_for_status()
+        .with_context(|| format!("downloading {url}"))?
+        .bytes()
+        .await?;
+    tokio::fs::write(pipe_dir.join(file_name), contents).await?;
+
+    Ok(pipe_dir)
+}
+
+async fn write_lock(pipe_dir: &std::path::Path, lock: PipeLock) -> Result<()> {
+    let raw = serde_json::to_string_pretty(&lock)?;
+    tokio::fs::write(pipe_dir.join("pipe.lock"), raw).await?;
+    Ok(())
+}
+
+fn github_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent("screenpipe")
+        .build()
+        .expect("building github http client")
+}
+
+/// Resolves a tag, branch, or (already-resolved) commit SHA to the commit
+/// SHA it points at, via GitHub's commits API.
+async fn resolve_ref(owner: &str, repo: &str, git_ref: &str) -> Result<String> {
+    #[derive(Deserialize)]
+    struct CommitResponse {
+        sha: String,
+    }
+
+    let url = format!("https://api.github.com/repos/{owner}/{repo}/commits/{git_ref}");
+    let commit: CommitResponse = github_client()
+        .get(&url)
+        .send()
+        .await?
+        .json()
+        .await
+        .with_context(|| format!("resolving ref {owner}/{repo}@{git_ref}"))?;
+    Ok(commit.sha)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct GithubContentEntry {
+    name: String,
+    r#type: String,
+    download_url: Option<String>,
+}
+
+/// Parses `https://github.com/<owner>/<repo>/tree/<ref>/<subpath>` into its
+/// parts. `<ref>` may be a branch, tag, or commit SHA.
+fn parse_github_tree_url(url: &str) -> Option<(String, String, String, String)> {
+    let rest = url.strip_prefix("https://github.com/")?;
+    let mut parts = rest.splitn(4, '/');
+    let owner = parts.next()?;
+    let repo = parts.next()?;
+    let tree = parts.next()?;
+    if tree != "tree" {
+        return None;
+    }
+    let ref_and_path = parts.next().unwrap_or_default();
+    let (git_ref, subpath) = ref_and_path.split_once('/').unwrap_or((ref_and_path, ""));
+    Some((
+        owner.to_string(),
+        repo.to_string(),
+        git_ref.to_string(),
+        subpath.to_string(),
+    ))
+}
+
+/// Parses `https://raw.githubusercontent.com/<owner>/<repo>/<ref>/<path>` into
+/// its parts.
+fn parse_raw_url(url: &str) -> Option<(String, String, String, String)> {
+    let rest = url.strip_prefix("https://raw.githubusercontent.com/")?;
+    let mut parts = rest.splitn(4, '/');
+    let owner = parts.next()?;
+    let repo = parts.next()?;
+    let git_ref = parts.next()?;
+    let path = parts.next()?;
+    Some((
+        owner.to_string(),
+        repo.to_string(),
+        git_ref.to_string(),
+        path.to_string(),
+    ))
+}
+
+fn raw_file_path_from_url(url: &str) -> Result<String> {
+    parse_raw_url(url)
+        .map(|(_, _, _, path)| path)
+        .ok_or_else(|| anyhow!("not a raw.githubusercontent.com url: {url}"))
+}
@@ -0,0 +1,19 @@
+mod bundler;
+mod error;
+mod fs;
+mod manager;
+mod permissions;
+mod process;
+mod runtime;
+mod watch;
+
+pub use error::{JsStackFrame, PipeError};
+pub use fs::{DirEntry, FileType, Metadata};
+pub use manager::{download_pipe, update_pipe};
+pub use process::SpawnOptions;
+pub use watch::{ChangeEvent, ChangeKind};
+pub use permissions::{
+    Capabilities, PermissionDenied, PermissionKind, PermissionManifest, PermissionPolicy,
+    PolicyRule,
+};
+pub use runtime::{run_js, run_pipe, run_pipe_with_policy};
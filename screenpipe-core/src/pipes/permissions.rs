@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// The `permissions` block of a pipe's `pipe.json`, declaring what the pipe
+/// asks to be allowed to do. This is untrusted input from the pipe author --
+/// it only ever *narrows* what a pipe can do, it never grants anything on
+/// its own. `run_pipe` intersects it with a host-supplied [`PermissionPolicy`]
+/// before anything is handed to the JS global.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PermissionManifest {
+    /// Hosts the pipe may reach with `pipe.get`, e.g. `"api.example.com"`.
+    /// An empty list means no network access.
+    pub net: Vec<String>,
+    /// Path prefixes (relative to `screenpipe_dir`) the pipe may read from.
+    pub read: Vec<String>,
+    /// Path prefixes (relative to `screenpipe_dir`) the pipe may write to.
+    pub write: Vec<String>,
+    /// Subprocess binaries the pipe may spawn, e.g. `"ffmpeg"`.
+    pub run: Vec<String>,
+}
+
+/// The policy a host applies on top of a pipe's requested [`PermissionManifest`].
+/// Think of this as "what the user allowed" vs. the manifest's "what the pipe
+/// asked for" -- the grant is always their intersection.
+#[derive(Debug, Clone, Default)]
+pub struct PermissionPolicy {
+    pub net: PolicyRule,
+    pub read: PolicyRule,
+    pub write: PolicyRule,
+    pub run: PolicyRule,
+}
+
+impl PermissionPolicy {
+    /// A policy that grants everything a pipe asks for. Useful for local
+    /// development and for pipes the user already trusts.
+    pub fn allow_all() -> Self {
+        Self {
+            net: PolicyRule::All,
+            read: PolicyRule::All,
+            write: PolicyRule::All,
+            run: PolicyRule::All,
+        }
+    }
+
+    /// A policy that grants nothing, regardless of what the manifest requests.
+    pub fn deny_all() -> Self {
+        Self::default()
+    }
+}
+
+/// A single dimension of a [`PermissionPolicy`]: either every requested value
+/// is allowed, or only an explicit allowlist is.
+#[derive(Debug, Clone, Default)]
+pub enum PolicyRule {
+    #[default]
+    None,
+    All,
+    Allow(HashSet<String>),
+}
+
+impl PolicyRule {
+    fn permits(&self, value: &str) -> bool {
+        match self {
+            PolicyRule::None => false,
+            PolicyRule::All => true,
+            PolicyRule::Allow(set) => set.contains(value),
+        }
+    }
+}
+
+/// What a pipe was actually granted after intersecting its [`PermissionManifest`]
+/// with the host's [`PermissionPolicy`]. This is what `pipe.capabilities()`
+/// reports back to the pipe (and what a future pipe store would show a user
+/// before they install it).
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub net: Vec<String>,
+    pub read: Vec<String>,
+    pub write: Vec<String>,
+    pub run: Vec<String>,
+}
+
+impl Capabilities {
+    /// Intersect a pipe's requested manifest with the host policy, keeping
+    /// only what both sides agree to.
+    pub fn negotiate(manifest: &PermissionManifest, policy: &PermissionPolicy) -> Self {
+        Self {
+            net: manifest
+                .net
+                .iter()
+                .filter(|h| policy.net.permits(h))
+                .cloned()
+                .collect(),
+            read: manifest
+                .read
+                .iter()
+                .filter(|p| policy.read.permits(p))
+                .cloned()
+                .collect(),
+            write: manifest
+                .write
+                .iter()
+                .filter(|p| policy.write.permits(p))
+                .cloned()
+                .collect(),
+            run: manifest
+                .run
+                .iter()
+                .filter(|c| policy.run.permits(c))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn allows_net(&self, host: &str) -> bool {
+        self.net.iter().any(|h| h == "*" || h == host)
+    }
+
+    pub fn allows_run(&self, command: &str) -> bool {
+        self.run.iter().any(|c| c == "*" || c == command)
+    }
+
+    /// Resolves `path` against `root`, rejecting `..` escapes, then checks it
+    /// against the granted read prefixes. Returns the resolved absolute path
+    /// on success.
+    pub fn check_read(&self, root: &Path, path: &str) -> Result<PathBuf, PermissionDenied> {
+        self.check_path(root, path, &self.read, PermissionKind::Read)
+    }
+
+    /// Same as [`Capabilities::check_read`] but against the granted write prefixes.
+    pub fn check_write(&self, root: &Path, path: &str) -> Result<PathBuf, PermissionDenied> {
+        self.check_path(root, path, &self.write, PermissionKind::Write)
+    }
+
+    fn check_path(
+        &self,
+        root: &Path,
+        path: &str,
+        prefixes: &[String],
+        kind: PermissionKind,
+    ) -> Result<PathBuf, PermissionDenied> {
+        let resolved = resolve_confined(root, path).ok_or_else(|| PermissionDenied {
+            kind,
+            detail: format!("path `{path}` escapes the pipe's sandbox"),
+        })?;
+
+        let granted = prefixes.iter().any(|prefix| {
+            if prefix == "*" {
+                return true;
+            }
+            let prefix_path = normalize(Path::new(prefix));
+            resolved.starts_with(root.join(&prefix_path))
+        });
+
+        if granted {
+            Ok(resolved)
+        } else {
+            Err(PermissionDenied {
+                kind,
+                detail: format!("`{path}` is not within an allowed {kind} prefix"),
+            })
+        }
+    }
+}
+
+/// Which permission dimension was denied. A denied op fails in the pipe with a
+/// plain JS `Error` whose message is the [`PermissionDenied`] display text,
+/// e.g. `PermissionDenied (read): ...`; it is not an instance of the
+/// `PermissionDeniedError` class that runtime.js defines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionKind {
+    Net,
+    Read,
+    Write,
+    Run,
+}
+
+impl fmt::Display for PermissionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            PermissionKind::Net => "net",
+            PermissionKind::Read => "read",
+            PermissionKind::Write => "write",
+            PermissionKind::Run => "run",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Thrown inside the pipe (as a JS error) when it attempts an operation its
+/// negotiated [`Capabilities`] doesn't cover.
+#[derive(Debug, Clone)]
+pub struct PermissionDenied {
+    pub kind: PermissionKind,
+    pub detail: String,
+}
+
+impl fmt::Display for PermissionDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PermissionDenied ({}): {}", self.kind, self.detail)
+    }
+}
+
+impl std::error::Error for PermissionDenied {}
+
+/// Joins `root` and `path`, rejecting any path with `..` components and any
+/// absolute path, even one that points inside `root`. The rejection check
+/// runs on the raw, un-normalized components -- `normalize` throws away
+/// `ParentDir`/`RootDir`/`Prefix` components, so checking after normalizing
+/// would never see them and would silently rewrite `../../etc/passwd` (or
+/// an absolute path) into a path under `root` instead of refusing it.
+fn resolve_confined(root: &Path, path: &str) -> Option<PathBuf> {
+    let raw = Path::new(path);
+    if raw
+        .components()
+        .any(|c| matches!(c, Component::ParentDir | Component::Prefix(_) | Component::RootDir))
+    {
+        return None;
+    }
+    let joined = root.join(normalize(raw));
+    joined.starts_with(root).then_some(joined)
+}
+
+/// Keeps only the normal (named) components, dropping root, prefix, `.` and
+/// `..` components wherever they appear, so relative manifest paths and
+/// pipe-requested paths compare the same way regardless of how they were written.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .collect()
+}
@@ -0,0 +1,404 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+use deno_core::{op2, OpState};
+use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+use serde::Deserialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::mpsc;
+
+use super::runtime::PipeRuntimeState;
+
+/// Options accepted by `pipe.spawn(cmd, args, opts)`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SpawnOptions {
+    pub env: HashMap<String, String>,
+    pub cwd: Option<String>,
+    pub pty: bool,
+}
+
+/// A running child process, either a plain piped `Command` or a PTY-backed
+/// one. `stdout`/`stderr` are drained into unbounded channels by background
+/// tasks so JS can poll them as an async iterator without blocking the isolate.
+enum ProcessBackend {
+    Plain {
+        // `None` once `status()` has taken ownership to `.await` on it.
+        child: Option<Child>,
+        stdin: Option<ChildStdin>,
+    },
+    Pty {
+        master: Box<dyn MasterPty + Send>,
+        writer: Box<dyn std::io::Write + Send>,
+        // `None` once `status()` has taken ownership to `.wait()` on it.
+        child: Option<Box<dyn portable_pty::Child + Send + Sync>>,
+    },
+}
+
+pub(crate) struct ProcessHandle {
+    backend: ProcessBackend,
+    stdout_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    stderr_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+}
+
+#[derive(Default)]
+pub(crate) struct ProcessRegistry {
+    next_id: u32,
+    handles: HashMap<u32, ProcessHandle>,
+}
+
+impl ProcessRegistry {
+    fn insert(&mut self, handle: ProcessHandle) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.handles.insert(id, handle);
+        id
+    }
+}
+
+fn pump_reader<R>(mut reader: R) -> mpsc::UnboundedReceiver<Vec<u8>>
+where
+    R: AsyncReadExt + Unpin + Send + 'static,
+{
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut buf = [0u8; 4096];
+        loop {
+            match reader.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if tx.send(buf[..n].to_vec()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    rx
+}
+
+fn spawn_plain(cmd: &str, args: &[String], opts: &SpawnOptions, cwd: Option<&Path>) -> Result<ProcessHandle> {
+    let mut command = Command::new(cmd);
+    command
+        .args(args)
+        .env_clear()
+        .envs(inherited_path())
+        .envs(&opts.env)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+
+    let mut child = command.spawn()?;
+    let stdout_rx = pump_reader(child.stdout.take().expect("piped stdout"));
+    let stderr_rx = pump_reader(child.stderr.take().expect("piped stderr"));
+    let stdin = child.stdin.take();
+
+    Ok(ProcessHandle {
+        backend: ProcessBackend::Plain {
+            child: Some(child),
+            stdin,
+        },
+        stdout_rx,
+        stderr_rx,
+    })
+}
+
+fn spawn_pty(cmd: &str, args: &[String], opts: &SpawnOptions, cwd: Option<&Path>) -> Result<ProcessHandle> {
+    let pty_system = native_pty_system();
+    let pair = pty_system.openpty(PtySize {
+        rows: 24,
+        cols: 80,
+        pixel_width: 0,
+        pixel_height: 0,
+    })?;
+
+    let mut builder = CommandBuilder::new(cmd);
+    builder.args(args);
+    builder.env_clear();
+    for (k, v) in inherited_path() {
+        builder.env(k, v);
+    }
+    for (k, v) in &opts.env {
+        builder.env(k, v);
+    }
+    if let Some(cwd) = cwd {
+        builder.cwd(cwd);
+    }
+
+    let child = pair.slave.spawn_command(builder)?;
+    let writer = pair.master.take_writer()?;
+    let mut reader = pair.master.try_clone_reader()?;
+
+    // A PTY is a single duplex fd from the OS's point of view, so stdout and
+    // stderr are merged -- stderr simply never produces anything.
+    let (stdout_tx, stdout_rx) = mpsc::unbounded_channel();
+    let (_, stderr_rx) = mpsc::unbounded_channel();
+    tokio::task::spawn_blocking(move || {
+        let mut buf = [0u8; 4096];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if stdout_tx.send(buf[..n].to_vec()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(ProcessHandle {
+        backend: ProcessBackend::Pty {
+            master: pair.master,
+            writer,
+            child: Some(child),
+        },
+        stdout_rx,
+        stderr_rx,
+    })
+}
+
+/// `Command`/`CommandBuilder` inherit the full host environment by default,
+/// which would hand every secret in the host's env to a "sandboxed" spawn.
+/// We `env_clear()` and rebuild from scratch, but `PATH` is still needed so a
+/// bare command name (`"sh"`, `"cat"`) resolves the same way it would
+/// un-sandboxed; a pipe that needs more than that has to pass it via `opts.env`.
+fn inherited_path() -> Vec<(String, String)> {
+    std::env::var("PATH")
+        .map(|path| vec![("PATH".to_string(), path)])
+        .unwrap_or_default()
+}
+
+/// Spawns a child process (optionally under a PTY) after checking `cmd`
+/// against the pipe's `run` capability and resolving `opts.cwd` through the
+/// pipe's `read` capability -- otherwise a pipe allowed to run even one
+/// allowlisted binary could point its cwd anywhere on the host filesystem,
+/// sidestepping the read/write manifest entirely.
+pub(crate) fn spawn(
+    state: &PipeRuntimeState,
+    cmd: &str,
+    args: &[String],
+    opts: &SpawnOptions,
+) -> Result<ProcessHandle> {
+    if !state.capabilities.allows_run(cmd) {
+        return Err(anyhow!(
+            "{}",
+            super::permissions::PermissionDenied {
+                kind: super::permissions::PermissionKind::Run,
+                detail: format!("`{cmd}` is not in the pipe's run allowlist"),
+            }
+        ));
+    }
+
+    let resolved_cwd = opts
+        .cwd
+        .as_deref()
+        .map(|cwd| {
+            state
+                .capabilities
+                .check_read(&state.screenpipe_dir, cwd)
+                .map_err(|e| anyhow!("{e}"))
+        })
+        .transpose()?;
+
+    if opts.pty {
+        spawn_pty(cmd, args, opts, resolved_cwd.as_deref())
+    } else {
+        spawn_plain(cmd, args, opts, resolved_cwd.as_deref())
+    }
+}
+
+#[op2(async)]
+async fn op_pipe_spawn(
+    state: Rc<RefCell<OpState>>,
+    #[string] cmd: String,
+    #[serde] args: Vec<String>,
+    #[serde] opts: SpawnOptions,
+) -> Result<u32> {
+    let handle = {
+        let state_ref = state.borrow();
+        let runtime_state = state_ref.borrow::<PipeRuntimeState>();
+        spawn(runtime_state, &cmd, &args, &opts)?
+    };
+
+    let mut state = state.borrow_mut();
+    if !state.has::<ProcessRegistry>() {
+        state.put(ProcessRegistry::default());
+    }
+    Ok(state.borrow_mut::<ProcessRegistry>().insert(handle))
+}
+
+#[op2(async)]
+#[buffer]
+async fn op_pipe_process_read_stdout(state: Rc<RefCell<OpState>>, id: u32) -> Result<Vec<u8>> {
+    read_stream(state, id, true).await
+}
+
+#[op2(async)]
+#[buffer]
+async fn op_pipe_process_read_stderr(state: Rc<RefCell<OpState>>, id: u32) -> Result<Vec<u8>> {
+    read_stream(state, id, false).await
+}
+
+/// Ops can't hold a `RefCell` borrow across an `.await`, so the receiver is
+/// briefly swapped out for a closed placeholder and put back once the next
+/// chunk (or stream end) arrives.
+async fn read_stream(state: Rc<RefCell<OpState>>, id: u32, stdout: bool) -> Result<Vec<u8>> {
+    let mut placeholder = mpsc::unbounded_channel().1;
+    {
+        let mut state = state.borrow_mut();
+        let registry = state.borrow_mut::<ProcessRegistry>();
+        let handle = registry
+            .handles
+            .get_mut(&id)
+            .ok_or_else(|| anyhow!("unknown process id {id}"))?;
+        let target = if stdout {
+            &mut handle.stdout_rx
+        } else {
+            &mut handle.stderr_rx
+        };
+        std::mem::swap(target, &mut placeholder);
+    }
+
+    let chunk = placeholder.recv().await.unwrap_or_default();
+
+    let mut state = state.borrow_mut();
+    if let Some(handle) = state.borrow_mut::<ProcessRegistry>().handles.get_mut(&id) {
+        let target = if stdout {
+            &mut handle.stdout_rx
+        } else {
+            &mut handle.stderr_rx
+        };
+        *target = placeholder;
+    }
+
+    Ok(chunk)
+}
+
+#[op2(async)]
+async fn op_pipe_process_write_stdin(
+    state: Rc<RefCell<OpState>>,
+    id: u32,
+    #[buffer] data: Vec<u8>,
+) -> Result<()> {
+    let mut state = state.borrow_mut();
+    let registry = state.borrow_mut::<ProcessRegistry>();
+    let handle = registry
+        .handles
+        .get_mut(&id)
+        .ok_or_else(|| anyhow!("unknown process id {id}"))?;
+
+    match &mut handle.backend {
+        ProcessBackend::Plain {
+            stdin: Some(stdin), ..
+        } => stdin.write_all(&data).await?,
+        ProcessBackend::Pty { writer, .. } => writer.write_all(&data)?,
+        _ => return Err(anyhow!("process {id} has no writable stdin")),
+    }
+    Ok(())
+}
+
+#[op2(fast)]
+fn op_pipe_process_resize_pty(state: &mut OpState, id: u32, rows: u16, cols: u16) -> Result<()> {
+    let registry = state.borrow_mut::<ProcessRegistry>();
+    let handle = registry
+        .handles
+        .get_mut(&id)
+        .ok_or_else(|| anyhow!("unknown process id {id}"))?;
+    match &handle.backend {
+        ProcessBackend::Pty { master, .. } => Ok(master.resize(PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?),
+        ProcessBackend::Plain { .. } => Err(anyhow!("process {id} was not spawned with pty: true")),
+    }
+}
+
+#[op2(fast)]
+fn op_pipe_process_kill(state: &mut OpState, id: u32) -> Result<()> {
+    let registry = state.borrow_mut::<ProcessRegistry>();
+    if let Some(handle) = registry.handles.get_mut(&id) {
+        match &mut handle.backend {
+            ProcessBackend::Plain {
+                child: Some(child), ..
+            } => {
+                let _ = child.start_kill();
+            }
+            ProcessBackend::Pty { child: Some(child), .. } => {
+                let _ = child.kill();
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// A child process taken out of its [`ProcessHandle`] so it can be awaited
+/// without holding the `OpState` borrow across the wait.
+enum TakenChild {
+    Plain(Child),
+    Pty(Box<dyn portable_pty::Child + Send + Sync>),
+}
+
+#[op2(async)]
+async fn op_pipe_process_status(state: Rc<RefCell<OpState>>, id: u32) -> Result<i32> {
+    let taken = {
+        let mut state = state.borrow_mut();
+        let registry = state.borrow_mut::<ProcessRegistry>();
+        let handle = registry
+            .handles
+            .get_mut(&id)
+            .ok_or_else(|| anyhow!("unknown process id {id}"))?;
+        match &mut handle.backend {
+            ProcessBackend::Plain { child, .. } => TakenChild::Plain(
+                child
+                    .take()
+                    .ok_or_else(|| anyhow!("status() already awaited for process {id}"))?,
+            ),
+            ProcessBackend::Pty { child, .. } => TakenChild::Pty(
+                child
+                    .take()
+                    .ok_or_else(|| anyhow!("status() already awaited for process {id}"))?,
+            ),
+        }
+    };
+
+    match taken {
+        TakenChild::Plain(mut child) => {
+            let status = child.wait().await?;
+            Ok(status.code().unwrap_or(-1))
+        }
+        TakenChild::Pty(mut child) => {
+            // `portable_pty::Child::wait` blocks the calling thread until the
+            // child exits, so it has to run on a blocking thread rather than
+            // directly in this async fn -- otherwise it'd stall the tokio
+            // worker for as long as the child runs.
+            let status = tokio::task::spawn_blocking(move || child.wait()).await??;
+            Ok(status.exit_code() as i32)
+        }
+    }
+}
+
+deno_core::extension!(
+    pipe_process,
+    ops = [
+        op_pipe_spawn,
+        op_pipe_process_read_stdout,
+        op_pipe_process_read_stderr,
+        op_pipe_process_write_stdin,
+        op_pipe_process_resize_pty,
+        op_pipe_process_kill,
+        op_pipe_process_status,
+    ],
+    esm_entry_point = "ext:pipe_process/process.js",
+    esm = [dir "src/pipes/js", "process.js"],
+);
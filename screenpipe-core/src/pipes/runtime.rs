@@ -0,0 +1,280 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use anyhow::{anyhow, Context, Result as AnyResult};
+use deno_core::{op2, Extension, JsRuntime, OpState, RuntimeOptions};
+use serde_json::Value;
+use sourcemap::SourceMap;
+use tracing::{debug, error, info};
+
+use super::error::PipeError;
+use super::permissions::{Capabilities, PermissionManifest, PermissionPolicy};
+
+/// Runs a single JS/TS file with no pipe directory context, e.g. an ad-hoc
+/// script. `_pipe_name` is currently unused but kept so the signature matches
+/// `run_pipe`'s and call sites don't need to special-case this path.
+pub async fn run_js(_pipe_name: &str, file_path: &str, screenpipe_dir: PathBuf) -> Result<(), PipeError> {
+    // Bundled like a pipe entrypoint, so a TS file is transpiled and
+    // top-level `await` runs inside the bundle's async wrapper.
+    let bundle = super::bundler::bundle(Path::new(file_path), &screenpipe_dir)
+        .await
+        .with_context(|| format!("bundling {file_path}"))
+        .map_err(PipeError::from)?;
+    let source_map = SourceMap::from_slice(bundle.source_map.as_bytes()).ok();
+
+    // A bare script gets the most permissive policy: there's no pipe.json to
+    // negotiate against, so it's treated the same as a trusted local tool.
+    let capabilities = Capabilities::negotiate(
+        &PermissionManifest {
+            net: vec!["*".into()],
+            read: vec!["*".into()],
+            write: vec!["*".into()],
+            run: vec!["*".into()],
+        },
+        &PermissionPolicy::allow_all(),
+    );
+
+    execute(
+        &bundle.code,
+        &screenpipe_dir,
+        capabilities,
+        Value::Object(Default::default()),
+        source_map.as_ref(),
+    )
+    .await
+}
+
+/// Runs a pipe directory: loads `pipe.json` (if present) for config and the
+/// `permissions` manifest, negotiates [`Capabilities`] against the host
+/// policy, and executes `pipe.ts`/`pipe.js` with only those capabilities
+/// wired into the JS global.
+pub async fn run_pipe(pipe_dir: String, screenpipe_dir: PathBuf) -> Result<(), PipeError> {
+    run_pipe_with_policy(pipe_dir, screenpipe_dir, PermissionPolicy::allow_all()).await
+}
+
+/// Same as [`run_pipe`] but lets the caller supply the host-side
+/// [`PermissionPolicy`] instead of defaulting to "allow everything the pipe
+/// asks for". This is the entry point a future pipe store would use once it
+/// starts asking users to approve permissions explicitly.
+pub async fn run_pipe_with_policy(
+    pipe_dir: String,
+    screenpipe_dir: PathBuf,
+    policy: PermissionPolicy,
+) -> Result<(), PipeError> {
+    let (code, capabilities, config, source_map) = prepare_pipe(&pipe_dir, &screenpipe_dir, &policy)
+        .await
+        .map_err(PipeError::from)?;
+
+    execute(&code, &screenpipe_dir, capabilities, config, source_map.as_ref()).await
+}
+
+/// Loads and transpiles a pipe's entrypoint and negotiates its capabilities.
+/// Split out from `run_pipe_with_policy` so the plumbing (which only ever
+/// fails with plain I/O/parse errors) stays on `anyhow`, while the actual
+/// script execution is what reports rich `PipeError`s.
+async fn prepare_pipe(
+    pipe_dir: &str,
+    screenpipe_dir: &Path,
+    policy: &PermissionPolicy,
+) -> AnyResult<(String, Capabilities, Value, Option<SourceMap>)> {
+    let pipe_dir = PathBuf::from(pipe_dir);
+
+    let entrypoint = ["pipe.ts", "pipe.js"]
+        .iter()
+        .map(|name| pipe_dir.join(name))
+        .find(|p| p.exists())
+        .ok_or_else(|| anyhow!("no pipe.ts or pipe.js found in {}", pipe_dir.display()))?;
+
+    let config: Value = match tokio::fs::read_to_string(pipe_dir.join("pipe.json")).await {
+        Ok(raw) => serde_json::from_str(&raw).with_context(|| "parsing pipe.json")?,
+        Err(_) => Value::Object(Default::default()),
+    };
+
+    // Capabilities are opt-in: a pipe that doesn't declare a `permissions`
+    // block at all gets nothing. The manifest is untrusted input from the
+    // pipe author, so defaulting a missing block to "everything" would let
+    // the one party this sandbox doesn't trust grant itself full access
+    // just by omitting the block that would otherwise narrow it.
+    let manifest: PermissionManifest = match config.get("permissions") {
+        Some(value) => {
+            serde_json::from_value(value.clone()).with_context(|| "parsing pipe.json permissions block")?
+        }
+        None => PermissionManifest::default(),
+    };
+
+    let capabilities = Capabilities::negotiate(&manifest, policy);
+    debug!(?capabilities, "negotiated pipe capabilities");
+
+    // Resolves local and `https://` imports starting from the entrypoint,
+    // transpiling and bundling the whole module graph into one script. A
+    // single-file pipe with no imports just bundles to itself.
+    let bundle = super::bundler::bundle(&entrypoint, screenpipe_dir)
+        .await
+        .with_context(|| format!("bundling {}", entrypoint.display()))?;
+    let source_map = SourceMap::from_slice(bundle.source_map.as_bytes()).ok();
+
+    Ok((bundle.code, capabilities, config, source_map))
+}
+
+async fn execute(
+    code: &str,
+    screenpipe_dir: &Path,
+    capabilities: Capabilities,
+    config: Value,
+    source_map: Option<&SourceMap>,
+) -> Result<(), PipeError> {
+    let ext = pipe_extension(screenpipe_dir.to_path_buf(), capabilities, config);
+
+    let mut runtime = JsRuntime::new(RuntimeOptions {
+        extensions: vec![
+            ext,
+            super::fs::pipe_fs::init_ops_and_esm(),
+            super::watch::pipe_watch::init_ops_and_esm(),
+            super::process::pipe_process::init_ops_and_esm(),
+        ],
+        ..Default::default()
+    });
+
+    info!("executing pipe script ({} bytes)", code.len());
+    let result = runtime
+        .execute_script("pipe.js", code.to_string())
+        .map_err(|e| pipe_error_from_v8(e, source_map))?;
+
+    {
+        let mut scope = runtime.handle_scope();
+        let local = deno_core::v8::Local::new(&mut scope, result);
+        debug!("pipe script result: {}", local.to_rust_string_lossy(&mut scope));
+    }
+
+    if let Err(e) = runtime.run_event_loop(Default::default()).await {
+        let pipe_err = pipe_error_from_v8(e, source_map);
+        error!("{}", pipe_err.colorized_dump());
+        return Err(pipe_err);
+    }
+
+    Ok(())
+}
+
+/// Converts an uncaught exception or rejected top-level promise surfaced by
+/// the isolate into a [`PipeError`], remapping its stack through `source_map`
+/// when one is available.
+fn pipe_error_from_v8(err: deno_core::error::AnyError, source_map: Option<&SourceMap>) -> PipeError {
+    match err.downcast_ref::<deno_core::error::JsError>() {
+        Some(js_error) => {
+            let stack = js_error.stack.clone().unwrap_or_default();
+            PipeError::from_v8_stack(js_error.exception_message.clone(), &stack, source_map)
+        }
+        None => PipeError {
+            message: err.to_string(),
+            frames: vec![],
+        },
+    }
+}
+
+deno_core::extension!(
+    pipe_runtime,
+    ops = [
+        op_pipe_fetch,
+        op_pipe_read_file,
+        op_pipe_write_file,
+        op_pipe_load_config,
+        op_pipe_capabilities,
+    ],
+    esm_entry_point = "ext:pipe_runtime/runtime.js",
+    esm = [dir "src/pipes/js", "runtime.js"],
+    state = |state, options: PipeRuntimeState| {
+        state.put(options);
+    },
+);
+
+/// Per-isolate state carrying the negotiated capabilities and the pipe's
+/// parsed `pipe.json`, so every op can check permissions before touching the
+/// network or filesystem, and `pipe.loadConfig()` has something to return.
+pub(crate) struct PipeRuntimeState {
+    pub screenpipe_dir: PathBuf,
+    pub capabilities: Capabilities,
+    pub config: Value,
+}
+
+fn pipe_extension(screenpipe_dir: PathBuf, capabilities: Capabilities, config: Value) -> Extension {
+    pipe_runtime::init_ops_and_esm(PipeRuntimeState {
+        screenpipe_dir,
+        capabilities,
+        config,
+    })
+}
+
+#[op2(async)]
+#[string]
+async fn op_pipe_fetch(state: Rc<std::cell::RefCell<OpState>>, #[string] url: String) -> AnyResult<String> {
+    let host = url::Url::parse(&url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .ok_or_else(|| anyhow!("invalid url: {url}"))?;
+
+    {
+        let state = state.borrow();
+        let runtime_state = state.borrow::<PipeRuntimeState>();
+        if !runtime_state.capabilities.allows_net(&host) {
+            return Err(anyhow!(
+                "{}",
+                super::permissions::PermissionDenied {
+                    kind: super::permissions::PermissionKind::Net,
+                    detail: format!("`{host}` is not in the pipe's net allowlist"),
+                }
+            ));
+        }
+    }
+
+    let body = reqwest::get(&url).await?.text().await?;
+    Ok(body)
+}
+
+#[op2(async)]
+#[string]
+async fn op_pipe_read_file(
+    state: Rc<std::cell::RefCell<OpState>>,
+    #[string] path: String,
+) -> AnyResult<String> {
+    let resolved = {
+        let state = state.borrow();
+        let runtime_state = state.borrow::<PipeRuntimeState>();
+        runtime_state
+            .capabilities
+            .check_read(&runtime_state.screenpipe_dir, &path)
+            .map_err(|e| anyhow!("{e}"))?
+    };
+    Ok(tokio::fs::read_to_string(resolved).await?)
+}
+
+#[op2(async)]
+async fn op_pipe_write_file(
+    state: Rc<std::cell::RefCell<OpState>>,
+    #[string] path: String,
+    #[string] contents: String,
+) -> AnyResult<()> {
+    let resolved = {
+        let state = state.borrow();
+        let runtime_state = state.borrow::<PipeRuntimeState>();
+        runtime_state
+            .capabilities
+            .check_write(&runtime_state.screenpipe_dir, &path)
+            .map_err(|e| anyhow!("{e}"))?
+    };
+    tokio::fs::write(resolved, contents).await?;
+    Ok(())
+}
+
+#[op2]
+#[string]
+fn op_pipe_load_config(state: &mut OpState) -> AnyResult<String> {
+    let runtime_state = state.borrow::<PipeRuntimeState>();
+    Ok(serde_json::to_string(&runtime_state.config)?)
+}
+
+#[op2]
+#[string]
+fn op_pipe_capabilities(state: &mut OpState) -> AnyResult<String> {
+    let runtime_state = state.borrow::<PipeRuntimeState>();
+    Ok(serde_json::to_string(&runtime_state.capabilities)?)
+}
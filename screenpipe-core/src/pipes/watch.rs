@@ -0,0 +1,184 @@
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use deno_core::{op2, OpState};
+use notify_debouncer_full::{
+    new_debouncer,
+    notify::{RecommendedWatcher, RecursiveMode},
+    DebounceEventResult, Debouncer, FileIdMap,
+};
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use super::runtime::PipeRuntimeState;
+
+/// What happened to the paths in a [`ChangeEvent`], named the way `notify`'s
+/// `EventKind` collapses down to what a pipe actually needs to react to.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+    Renamed,
+}
+
+/// One batch of filesystem changes delivered to a pipe's `pipe.watch` stream.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEvent {
+    pub kind: ChangeKind,
+    pub paths: Vec<String>,
+}
+
+/// A live watch started by `pipe.watch`. Dropping it (or calling
+/// [`WatchHandle::stop`]) tears down the underlying debounced watcher so a
+/// long-running pipe doesn't leak inotify/FSEvents handles across reloads.
+pub struct WatchHandle {
+    _debouncer: Debouncer<RecommendedWatcher, FileIdMap>,
+    pub receiver: mpsc::UnboundedReceiver<ChangeEvent>,
+}
+
+impl WatchHandle {
+    pub fn stop(self) {
+        // Dropping `_debouncer` unregisters the OS watch; nothing else to do.
+    }
+}
+
+/// Starts a debounced recursive-or-flat watch rooted at `path`, which must
+/// already have passed the pipe's `read` permission check -- watching is a
+/// read-only operation over the sandbox, so it's gated the same way.
+pub fn watch(path: PathBuf, recursive: bool, debounce: Duration) -> Result<WatchHandle> {
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    let mut debouncer = new_debouncer(debounce, None, move |result: DebounceEventResult| {
+        let Ok(events) = result else { return };
+        for event in events {
+            let Some(kind) = map_kind(&event.event.kind) else {
+                continue;
+            };
+            let paths = event
+                .event
+                .paths
+                .iter()
+                .map(|p| p.to_string_lossy().into_owned())
+                .collect();
+            let _ = tx.send(ChangeEvent { kind, paths });
+        }
+    })?;
+
+    let mode = if recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    debouncer.watcher().watch(&path, mode)?;
+
+    Ok(WatchHandle {
+        _debouncer: debouncer,
+        receiver: rx,
+    })
+}
+
+fn map_kind(kind: &notify_debouncer_full::notify::EventKind) -> Option<ChangeKind> {
+    use notify_debouncer_full::notify::EventKind;
+    match kind {
+        EventKind::Create(_) => Some(ChangeKind::Created),
+        EventKind::Modify(notify_debouncer_full::notify::event::ModifyKind::Name(_)) => {
+            Some(ChangeKind::Renamed)
+        }
+        EventKind::Modify(_) => Some(ChangeKind::Modified),
+        EventKind::Remove(_) => Some(ChangeKind::Removed),
+        _ => None,
+    }
+}
+
+/// Registry of watches started by the pipe currently executing, keyed by the
+/// JS-visible watch id returned from `op_pipe_watch_start`. Torn down
+/// wholesale when the pipe's isolate is dropped.
+#[derive(Default)]
+pub(crate) struct WatchRegistry {
+    next_id: u32,
+    handles: std::collections::HashMap<u32, WatchHandle>,
+}
+
+impl WatchRegistry {
+    fn insert(&mut self, handle: WatchHandle) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.handles.insert(id, handle);
+        id
+    }
+}
+
+#[op2(async)]
+async fn op_pipe_watch_start(
+    state: Rc<RefCell<OpState>>,
+    #[string] path: String,
+    recursive: bool,
+    debounce_ms: u32,
+) -> Result<u32> {
+    let resolved = {
+        let state = state.borrow();
+        let runtime_state = state.borrow::<PipeRuntimeState>();
+        runtime_state
+            .capabilities
+            .check_read(&runtime_state.screenpipe_dir, &path)
+            .map_err(|e| anyhow!("{e}"))?
+    };
+
+    let handle = watch(resolved, recursive, Duration::from_millis(debounce_ms as u64))?;
+
+    let mut state = state.borrow_mut();
+    if !state.has::<WatchRegistry>() {
+        state.put(WatchRegistry::default());
+    }
+    Ok(state.borrow_mut::<WatchRegistry>().insert(handle))
+}
+
+#[op2(async)]
+#[serde]
+async fn op_pipe_watch_next(
+    state: Rc<RefCell<OpState>>,
+    watch_id: u32,
+) -> Result<Option<ChangeEvent>> {
+    let mut receiver = {
+        let mut state = state.borrow_mut();
+        let registry = state.borrow_mut::<WatchRegistry>();
+        let handle = registry
+            .handles
+            .get_mut(&watch_id)
+            .ok_or_else(|| anyhow!("unknown watch id {watch_id}"))?;
+        // Ops can't hold a borrow across an .await, so briefly take the
+        // receiver out and put it back once we have the next event.
+        std::mem::replace(&mut handle.receiver, mpsc::unbounded_channel().1)
+    };
+
+    let next = receiver.recv().await;
+
+    let mut state = state.borrow_mut();
+    if let Some(handle) = state.borrow_mut::<WatchRegistry>().handles.get_mut(&watch_id) {
+        handle.receiver = receiver;
+    }
+
+    Ok(next)
+}
+
+#[op2(fast)]
+fn op_pipe_watch_stop(state: &mut OpState, watch_id: u32) {
+    if let Some(registry) = state.try_borrow_mut::<WatchRegistry>() {
+        if let Some(handle) = registry.handles.remove(&watch_id) {
+            handle.stop();
+        }
+    }
+}
+
+deno_core::extension!(
+    pipe_watch,
+    ops = [op_pipe_watch_start, op_pipe_watch_next, op_pipe_watch_stop],
+    esm_entry_point = "ext:pipe_watch/watch.js",
+    esm = [dir "src/pipes/js", "watch.js"],
+);